@@ -0,0 +1,231 @@
+use crate::parser::Expr;
+use crate::token::Number;
+use anyhow::{anyhow, bail, Result};
+
+/// A single instruction for the stack-based virtual machine in [`exec`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Push(i64),
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    Shl,
+    Shr,
+    Mod,
+    Pow,
+    Abs,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/// Lowers an `Expr` into bytecode for the stack machine, as an alternative to
+/// the tree-walking `Expr::eval`. This backend is integer-only: variables,
+/// assignments, float literals and calls to functions other than `abs` and
+/// `pow` (which the parser turns into `Expr::Abs`/`Expr::Pow`) have no bytecode
+/// equivalent and are rejected.
+///
+/// # Errors
+/// Returns an `anyhow::Error` if `expr` contains a variable, assignment,
+/// `Expr::Call` (`sqrt`, `min`, `max`, or any other function), or float literal.
+pub fn compile(expr: &Expr) -> Result<Vec<Instr>> {
+    let mut code = Vec::new();
+    lower(expr, &mut code)?;
+    Ok(code)
+}
+
+/// Post-order lowering: for a binary node, emit the left operand, then the
+/// right, then the operator instruction; for a unary node, emit the operand
+/// then the operator.
+fn lower(expr: &Expr, code: &mut Vec<Instr>) -> Result<()> {
+    match expr {
+        Expr::Num(Number::Int(n)) => code.push(Instr::Push(*n)),
+        Expr::Num(Number::Float(_)) => bail!("VM backend does not support floats"),
+        Expr::Neg(e) => {
+            lower(e, code)?;
+            code.push(Instr::Neg);
+        }
+        Expr::Add(a, b) => lower_binary(a, b, Instr::Add, code)?,
+        Expr::Sub(a, b) => lower_binary(a, b, Instr::Sub, code)?,
+        Expr::Mul(a, b) => lower_binary(a, b, Instr::Mul, code)?,
+        Expr::Div(a, b) => lower_binary(a, b, Instr::Div, code)?,
+        Expr::BitAnd(a, b) => lower_binary(a, b, Instr::BitAnd, code)?,
+        Expr::BitOr(a, b) => lower_binary(a, b, Instr::BitOr, code)?,
+        Expr::BitXor(a, b) => lower_binary(a, b, Instr::BitXor, code)?,
+        Expr::Shl(a, b) => lower_binary(a, b, Instr::Shl, code)?,
+        Expr::Shr(a, b) => lower_binary(a, b, Instr::Shr, code)?,
+        Expr::Mod(a, b) => lower_binary(a, b, Instr::Mod, code)?,
+        Expr::Pow(a, b) => lower_binary(a, b, Instr::Pow, code)?,
+        Expr::BitNot(e) => {
+            lower(e, code)?;
+            code.push(Instr::BitNot);
+        }
+        Expr::Abs(e) => {
+            lower(e, code)?;
+            code.push(Instr::Abs);
+        }
+        Expr::Lt(a, b) => lower_binary(a, b, Instr::Lt, code)?,
+        Expr::Le(a, b) => lower_binary(a, b, Instr::Le, code)?,
+        Expr::Gt(a, b) => lower_binary(a, b, Instr::Gt, code)?,
+        Expr::Ge(a, b) => lower_binary(a, b, Instr::Ge, code)?,
+        Expr::Eq(a, b) => lower_binary(a, b, Instr::Eq, code)?,
+        Expr::Ne(a, b) => lower_binary(a, b, Instr::Ne, code)?,
+        Expr::Var(_) | Expr::Assign(_, _) | Expr::Call(_, _) => {
+            bail!("VM backend does not support variables or function calls")
+        }
+    }
+    Ok(())
+}
+
+fn lower_binary(a: &Expr, b: &Expr, instr: Instr, code: &mut Vec<Instr>) -> Result<()> {
+    lower(a, code)?;
+    lower(b, code)?;
+    code.push(instr);
+    Ok(())
+}
+
+/// Executes bytecode produced by [`compile`] on a `Vec<i64>` operand stack,
+/// applying the same overflow-checked/div-by-zero semantics as `Expr::eval`.
+/// Each binary instruction pops the right operand first, then the left.
+///
+/// # Errors
+/// Returns an `anyhow::Error` on overflow, division/modulo by zero, a negative
+/// exponent, or malformed bytecode (stack underflow, or not exactly one value
+/// left on the stack at the end).
+pub fn exec(code: &[Instr]) -> Result<i64> {
+    let mut stack: Vec<i64> = Vec::new();
+    for instr in code {
+        match instr {
+            Instr::Push(n) => stack.push(*n),
+            Instr::Neg => {
+                let a = pop(&mut stack)?;
+                stack.push(a.checked_neg().ok_or(anyhow!("Overflow on negation"))?);
+            }
+            Instr::Add => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(a.checked_add(b).ok_or(anyhow!("Overflow on addition"))?);
+            }
+            Instr::Sub => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(a.checked_sub(b).ok_or(anyhow!("Overflow on substraction"))?);
+            }
+            Instr::Mul => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(
+                    a.checked_mul(b)
+                        .ok_or(anyhow!("Overflow on multiplication"))?,
+                );
+            }
+            Instr::Div => {
+                let (a, b) = pop_pair(&mut stack)?;
+                if b == 0 {
+                    bail!("Division by zero")
+                }
+                stack.push(a.checked_div(b).ok_or(anyhow!("Overflow on division"))?);
+            }
+            Instr::BitAnd => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(a & b);
+            }
+            Instr::BitOr => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(a | b);
+            }
+            Instr::BitXor => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(a ^ b);
+            }
+            Instr::BitNot => {
+                let a = pop(&mut stack)?;
+                stack.push(!a);
+            }
+            Instr::Shl => {
+                let (a, b) = pop_pair(&mut stack)?;
+                let shift = u32::try_from(b).map_err(|_| anyhow!("Overflow on left shift"))?;
+                stack.push(
+                    a.checked_shl(shift)
+                        .ok_or(anyhow!("Overflow on left shift"))?,
+                );
+            }
+            Instr::Shr => {
+                let (a, b) = pop_pair(&mut stack)?;
+                let shift = u32::try_from(b).map_err(|_| anyhow!("Overflow on right shift"))?;
+                stack.push(
+                    a.checked_shr(shift)
+                        .ok_or(anyhow!("Overflow on right shift"))?,
+                );
+            }
+            Instr::Mod => {
+                let (a, b) = pop_pair(&mut stack)?;
+                if b == 0 {
+                    bail!("Modulo by zero")
+                }
+                stack.push(a.checked_rem(b).ok_or(anyhow!("Overflow on modulo"))?);
+            }
+            Instr::Pow => {
+                let (a, b) = pop_pair(&mut stack)?;
+                if b < 0 {
+                    bail!("Negative exponent")
+                }
+                let exponent =
+                    u32::try_from(b).map_err(|_| anyhow!("Overflow on exponentiation"))?;
+                stack.push(
+                    a.checked_pow(exponent)
+                        .ok_or(anyhow!("Overflow on exponentiation"))?,
+                );
+            }
+            Instr::Abs => {
+                let a = pop(&mut stack)?;
+                stack.push(a.checked_abs().ok_or(anyhow!("Overflow on abs"))?);
+            }
+            Instr::Lt => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(i64::from(a < b));
+            }
+            Instr::Le => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(i64::from(a <= b));
+            }
+            Instr::Gt => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(i64::from(a > b));
+            }
+            Instr::Ge => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(i64::from(a >= b));
+            }
+            Instr::Eq => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(i64::from(a == b));
+            }
+            Instr::Ne => {
+                let (a, b) = pop_pair(&mut stack)?;
+                stack.push(i64::from(a != b));
+            }
+        }
+    }
+    match stack.len() {
+        1 => Ok(stack[0]),
+        n => bail!("Malformed bytecode: {} values left on the stack", n),
+    }
+}
+
+fn pop(stack: &mut Vec<i64>) -> Result<i64> {
+    stack.pop().ok_or(anyhow!("Malformed bytecode: stack underflow"))
+}
+
+/// Pops the right operand, then the left, returning them as `(left, right)`.
+fn pop_pair(stack: &mut Vec<i64>) -> Result<(i64, i64)> {
+    let right = pop(stack)?;
+    let left = pop(stack)?;
+    Ok((left, right))
+}
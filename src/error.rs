@@ -0,0 +1,83 @@
+use crate::token::Token;
+use std::fmt;
+
+/// Structured errors produced while tokenizing, parsing, or evaluating an
+/// expression. Syntax errors carry the character offset in the input where
+/// they were detected, via [`CalcError::pos`], so callers can render a
+/// caret-pointed diagnostic under the offending input.
+#[derive(Debug)]
+pub enum CalcError {
+    UnexpectedChar { ch: char, pos: usize },
+    UnexpectedToken { found: Token, pos: usize },
+    TrailingInput { found: Token, pos: usize },
+    UnmatchedParen { pos: usize },
+    InvalidAssignTarget { pos: usize },
+    InvalidNumber { pos: usize, message: String },
+    DivisionByZero,
+    ModuloByZero,
+    NegativeExponent,
+    NonIntegerOperand { op: &'static str },
+    Overflow { op: &'static str },
+    UnknownVariable { name: String },
+    UnknownFunction { name: String },
+    WrongArgCount {
+        name: &'static str,
+        expected: &'static str,
+        got: usize,
+    },
+}
+
+impl CalcError {
+    /// The character offset of the input this error points at, if any.
+    pub fn pos(&self) -> Option<usize> {
+        match self {
+            CalcError::UnexpectedChar { pos, .. }
+            | CalcError::UnexpectedToken { pos, .. }
+            | CalcError::TrailingInput { pos, .. }
+            | CalcError::UnmatchedParen { pos }
+            | CalcError::InvalidAssignTarget { pos }
+            | CalcError::InvalidNumber { pos, .. } => Some(*pos),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::UnexpectedChar { ch, pos } => {
+                write!(f, "Unexpected character '{ch}' at position {pos}")
+            }
+            CalcError::UnexpectedToken { found, pos } => {
+                write!(f, "Unexpected token in factor: {found:?} at position {pos}")
+            }
+            CalcError::TrailingInput { found, pos } => {
+                write!(f, "Unexpected trailing token {found:?} at position {pos}")
+            }
+            CalcError::UnmatchedParen { pos } => {
+                write!(f, "Missing closing ')' for '(' opened at position {pos}")
+            }
+            CalcError::InvalidAssignTarget { pos } => write!(
+                f,
+                "Left-hand side of '=' must be a variable at position {pos}"
+            ),
+            CalcError::InvalidNumber { pos, message } => {
+                write!(f, "Invalid number literal at position {pos}: {message}")
+            }
+            CalcError::DivisionByZero => write!(f, "Division by zero"),
+            CalcError::ModuloByZero => write!(f, "Modulo by zero"),
+            CalcError::NegativeExponent => write!(f, "Negative exponent"),
+            CalcError::NonIntegerOperand { op } => write!(f, "{op} requires integer operands"),
+            CalcError::Overflow { op } => write!(f, "Overflow on {op}"),
+            CalcError::UnknownVariable { name } => write!(f, "Unknown variable: {name}"),
+            CalcError::UnknownFunction { name } => write!(f, "Unknown function: {name}"),
+            CalcError::WrongArgCount {
+                name,
+                expected,
+                got,
+            } => write!(f, "{name}() expects {expected}, got {got}"),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
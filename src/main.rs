@@ -1,8 +1,11 @@
-use crate::parser::Parser;
+use crate::error::CalcError;
+use crate::parser::{Env, Parser};
 use clap::Parser as ClapParser;
 use tracing::{info, Level};
 use tracing_subscriber::fmt;
 
+mod compile;
+mod error;
 mod parser;
 mod token;
 
@@ -12,6 +15,12 @@ struct Args {
     /// The log verbosity level
     #[clap(short, long)]
     pub verbosity: Level,
+
+    /// Evaluate using the stack-based bytecode VM instead of the tree-walking
+    /// interpreter. The VM backend is integer-only: it rejects variables,
+    /// assignments, float literals and calls to functions other than `abs` and `pow`.
+    #[clap(long)]
+    pub vm: bool,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -19,78 +28,170 @@ fn main() -> anyhow::Result<()> {
     // Setup logger
     let subscriber = fmt().with_max_level(args.verbosity).finish();
     tracing::subscriber::set_global_default(subscriber).expect("Failed to set subscriber");
+    // Persisted across iterations so variables assigned on one line are visible on the next.
+    let mut env = Env::new();
     loop {
         let mut buffer = String::new();
         std::io::stdin().read_line(&mut buffer)?;
         let input = buffer.trim();
-        match calculate(input) {
+        let outcome = if args.vm {
+            calculate_vm(input).map(|n| n.to_string())
+        } else {
+            calculate(input, &mut env).map(|n| n.to_string())
+        };
+        match outcome {
             Ok(result) => println!("{} = {}", input, result),
-            Err(err) => println!("An error occurred while calculating: {}: {}", input, err),
+            Err(err) => print_error(input, &err),
         }
     }
 }
 
-fn calculate(input: &str) -> anyhow::Result<i64> {
+/// Prints the input line, a caret pointing at the offending position (when the
+/// underlying error is a `CalcError` carrying one), and the error message itself.
+fn print_error(input: &str, err: &anyhow::Error) {
+    let prefix = "An error occurred while calculating: ";
+    println!("{}{}", prefix, input);
+    if let Some(pos) = err.downcast_ref::<CalcError>().and_then(CalcError::pos) {
+        // Indent past the prefix so the caret sits under the offending char.
+        println!("{}^", " ".repeat(prefix.len() + pos));
+    }
+    println!("{}", err);
+}
+
+fn calculate(input: &str, env: &mut Env) -> anyhow::Result<crate::token::Number> {
     info!("{input}");
     let mut parser = Parser::new(input)?;
     // Parse the input and return the expression
-    let expr = parser.parse_expr()?;
+    let expr = parser.parse_statement()?;
     info!("{expr:?}");
     // Evaluate the expression
-    let res = expr.eval()?;
+    let res = expr.eval(env)?;
+    Ok(res)
+}
+
+fn calculate_vm(input: &str) -> anyhow::Result<i64> {
+    info!("{input}");
+    let mut parser = Parser::new(input)?;
+    // Parse the input and return the expression
+    let expr = parser.parse_statement()?;
+    info!("{expr:?}");
+    // Lower to bytecode and run it on the stack machine
+    let code = compile::compile(&expr)?;
+    let res = compile::exec(&code)?;
     Ok(res)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::calculate;
+    use crate::calculate_vm;
+    use crate::parser::Env;
+    use crate::token::Number;
     use std::i64;
 
     #[test]
     fn test_calculate_simple() {
         let input = "-1+5*(2+1)-3";
-        let result = calculate(input).unwrap();
-        assert_eq!(11, result);
+        let result = calculate(input, &mut Env::new()).unwrap();
+        assert_eq!(Number::Int(11), result);
     }
 
     #[test]
     fn test_calculate_double_parenthesis() {
         let input = "-2+5*((10+5)*3)+8-14/2";
-        let result = calculate(input).unwrap();
-        assert_eq!(224, result);
+        let result = calculate(input, &mut Env::new()).unwrap();
+        assert_eq!(Number::Int(224), result);
     }
 
     #[test]
     fn test_calculate_dib_by_zero() {
         let input = "-2+10/(5-5)";
-        let err = calculate(input).unwrap_err();
+        let err = calculate(input, &mut Env::new()).unwrap_err();
         assert_eq!("Division by zero", format!("{}", err));
     }
 
     #[test]
     fn test_calculate_negative_result() {
         let input = "5*(3-5)+1";
-        let result = calculate(input).unwrap();
-        assert_eq!(-9, result);
+        let result = calculate(input, &mut Env::new()).unwrap();
+        assert_eq!(Number::Int(-9), result);
     }
 
     #[test]
     fn test_calculate_spaces() {
         let input = "5   *(3-  5) +1";
-        let result = calculate(input).unwrap();
-        assert_eq!(-9, result);
+        let result = calculate(input, &mut Env::new()).unwrap();
+        assert_eq!(Number::Int(-9), result);
+    }
+
+    #[test]
+    fn test_calculate_float() {
+        let mut env = Env::new();
+        let input = "10/3";
+        let result = calculate(input, &mut env).unwrap();
+        assert_eq!(Number::Float(10.0 / 3.0), result);
+
+        let input = "10/2";
+        let result = calculate(input, &mut env).unwrap();
+        assert_eq!(Number::Int(5), result);
+
+        let input = "1.5+2.5";
+        let result = calculate(input, &mut env).unwrap();
+        assert_eq!(Number::Float(4.0), result);
+
+        let input = "1e3";
+        let result = calculate(input, &mut env).unwrap();
+        assert_eq!(Number::Float(1000.0), result);
+
+        let input = "1.5e2 + 2e-2";
+        let result = calculate(input, &mut env).unwrap();
+        assert_eq!(Number::Float(150.02), result);
+    }
+
+    #[test]
+    fn test_calculate_radix_literals() {
+        let mut env = Env::new();
+        assert_eq!(Number::Int(255), calculate("0xFF", &mut env).unwrap());
+        assert_eq!(Number::Int(10), calculate("0b1010", &mut env).unwrap());
+        assert_eq!(Number::Int(8), calculate("0o10", &mut env).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_bitwise_operators() {
+        let mut env = Env::new();
+        assert_eq!(Number::Int(10), calculate("0xFF & 0b1010", &mut env).unwrap());
+        assert_eq!(
+            Number::Int(26),
+            calculate("0xFF & 0b1010 | (1 << 4)", &mut env).unwrap()
+        );
+        assert_eq!(Number::Int(6), calculate("5 ^ 3", &mut env).unwrap());
+        assert_eq!(Number::Int(-6), calculate("~5", &mut env).unwrap());
+        assert_eq!(Number::Int(40), calculate("5 << 3", &mut env).unwrap());
+        assert_eq!(Number::Int(2), calculate("20 >> 3", &mut env).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_modulo() {
+        let mut env = Env::new();
+        assert_eq!(Number::Int(1), calculate("10 % 3", &mut env).unwrap());
+        let err = calculate("10 % 0", &mut env).unwrap_err();
+        assert_eq!("Modulo by zero", format!("{}", err));
     }
 
     #[test]
     fn test_calculate_wrong_input() {
-        let input = "5**(3-  5) +1";
-        let err = calculate(input).unwrap_err();
-        assert_eq!("Unexpected token in factor: Asterisk", format!("{}", err));
+        let mut env = Env::new();
+        let input = "5***(3-  5) +1";
+        let err = calculate(input, &mut env).unwrap_err();
+        assert_eq!(
+            "Unexpected token in factor: Asterisk at position 3",
+            format!("{}", err)
+        );
 
         let input = "5*)(3-  5) +1";
-        let err = calculate(input).unwrap_err();
+        let err = calculate(input, &mut env).unwrap_err();
         assert_eq!(
-            "Unexpected token in factor: RightParenthesis",
+            "Unexpected token in factor: RightParenthesis at position 2",
             format!("{}", err)
         );
     }
@@ -98,17 +199,140 @@ mod tests {
     #[test]
     fn test_calculate_unmatched_parenthesis() {
         let input = "1+((2*3)+2";
-        let _err = calculate(input).unwrap_err();
+        let err = calculate(input, &mut Env::new()).unwrap_err();
+        assert_eq!(
+            "Missing closing ')' for '(' opened at position 2",
+            format!("{}", err)
+        );
     }
 
     #[test]
     fn test_calculate_overflow() {
+        let mut env = Env::new();
         let input = format!("{}+{}", i64::MAX, i64::MAX);
-        let err = calculate(&input).unwrap_err();
+        let err = calculate(&input, &mut env).unwrap_err();
         assert_eq!("Overflow on addition", format!("{}", err));
 
         let input = format!("{}+1", i64::MIN);
-        let err = calculate(&input).unwrap_err();
-        assert_eq!("number too large to fit in target type", format!("{}", err));
+        let err = calculate(&input, &mut env).unwrap_err();
+        assert_eq!(
+            "Invalid number literal at position 1: number too large to fit in target type",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_calculate_variables() {
+        let mut env = Env::new();
+        let result = calculate("x = 5", &mut env).unwrap();
+        assert_eq!(Number::Int(5), result);
+
+        let result = calculate("x * 2 + 1", &mut env).unwrap();
+        assert_eq!(Number::Int(11), result);
+
+        let err = calculate("y", &mut env).unwrap_err();
+        assert_eq!("Unknown variable: y", format!("{}", err));
+    }
+
+    #[test]
+    fn test_calculate_builtin_functions() {
+        let mut env = Env::new();
+        assert_eq!(
+            Number::Int(5),
+            calculate("abs(-5)", &mut env).unwrap()
+        );
+        assert_eq!(
+            Number::Int(8),
+            calculate("pow(2, 3)", &mut env).unwrap()
+        );
+        assert_eq!(
+            Number::Float(3.0),
+            calculate("sqrt(9)", &mut env).unwrap()
+        );
+        assert_eq!(
+            Number::Int(1),
+            calculate("min(5, 1, 3)", &mut env).unwrap()
+        );
+        assert_eq!(
+            Number::Int(5),
+            calculate("max(5, 1, 3)", &mut env).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_pow_exponent_errors() {
+        let mut env = Env::new();
+        let err = calculate("pow(2, -1)", &mut env).unwrap_err();
+        assert_eq!("Negative exponent", format!("{}", err));
+
+        // A positive exponent that's simply too large for `u32` is an overflow,
+        // not a negative exponent.
+        let err = calculate("pow(2, 5000000000)", &mut env).unwrap_err();
+        assert_eq!("Overflow on exponentiation", format!("{}", err));
+    }
+
+    #[test]
+    fn test_calculate_comparisons() {
+        let mut env = Env::new();
+        assert_eq!(Number::Int(1), calculate("3+4 > 5", &mut env).unwrap());
+        assert_eq!(Number::Int(0), calculate("3+4 < 5", &mut env).unwrap());
+        assert_eq!(Number::Int(1), calculate("5 >= 5", &mut env).unwrap());
+        assert_eq!(Number::Int(1), calculate("5 <= 5", &mut env).unwrap());
+        assert_eq!(Number::Int(1), calculate("5 == 5.0", &mut env).unwrap());
+        assert_eq!(Number::Int(1), calculate("5 != 6", &mut env).unwrap());
+
+        let result = calculate("x = 3+4 > 5", &mut env).unwrap();
+        assert_eq!(Number::Int(1), result);
+        assert_eq!(Number::Int(1), *env.get("x").unwrap());
+    }
+
+    #[test]
+    fn test_calculate_comparisons_do_not_chain() {
+        let err = calculate("1 < 2 < 3", &mut Env::new()).unwrap_err();
+        assert_eq!(
+            "Unexpected trailing token Lt at position 6",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_calculate_comparisons_large_integers() {
+        // Regression test: comparisons must compare two `Number::Int`s exactly
+        // rather than widening to `f64` first, which loses precision above 2^53.
+        let mut env = Env::new();
+        let input = format!("{} > {}", i64::MAX, i64::MAX - 1);
+        assert_eq!(Number::Int(1), calculate(&input, &mut env).unwrap());
+
+        assert_eq!(
+            Number::Int(0),
+            calculate("9007199254740993 == 9007199254740992", &mut env).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_vm_matches_tree_walker() {
+        let inputs = [
+            "-1+5*(2+1)-3",
+            "-2+5*((10+5)*3)+8-14/2",
+            "5*(3-5)+1",
+            "0xFF & 0b1010 | (1 << 4)",
+            "abs(-7) + 10 % 3 - 2**3",
+            "3+4 > 5",
+            "10 == 2*5",
+        ];
+        for input in inputs {
+            let tree_result = calculate(input, &mut Env::new()).unwrap();
+            let vm_result = calculate_vm(input).unwrap();
+            assert_eq!(Number::Int(vm_result), tree_result);
+        }
+    }
+
+    #[test]
+    fn test_calculate_vm_rejects_variables() {
+        let err = calculate_vm("x = 5").unwrap_err();
+        assert_eq!(
+            "VM backend does not support variables or function calls",
+            format!("{}", err)
+        );
     }
 }
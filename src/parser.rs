@@ -1,55 +1,263 @@
+use crate::error::CalcError;
 use crate::token::*;
-use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
 use tracing::{debug, trace};
 
+/// The evaluation environment: a mapping of variable names to their last assigned value.
+pub type Env = HashMap<String, Number>;
+
 /// AST formation which supports overflow-safe operations using `checked_*` methods
 #[derive(Clone, Debug)]
 pub enum Expr {
-    Int(i64),
+    Num(Number),
+    Var(String),
+    Assign(String, Box<Expr>),
+    Call(String, Vec<Expr>),
     Neg(Box<Expr>),
     Add(Box<Expr>, Box<Expr>),
     Sub(Box<Expr>, Box<Expr>),
     Mul(Box<Expr>, Box<Expr>),
     Div(Box<Expr>, Box<Expr>),
+    BitAnd(Box<Expr>, Box<Expr>),
+    BitOr(Box<Expr>, Box<Expr>),
+    BitXor(Box<Expr>, Box<Expr>),
+    BitNot(Box<Expr>),
+    Shl(Box<Expr>, Box<Expr>),
+    Shr(Box<Expr>, Box<Expr>),
+    Mod(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    Abs(Box<Expr>),
+    Lt(Box<Expr>, Box<Expr>),
+    Le(Box<Expr>, Box<Expr>),
+    Gt(Box<Expr>, Box<Expr>),
+    Ge(Box<Expr>, Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Ne(Box<Expr>, Box<Expr>),
 }
 
 impl Expr {
-    /// Recursively evaluates the expression returning an `i64` or an error.
+    /// Recursively evaluates the expression against `env`, returning a `Number` or an error.
+    ///
+    /// Integer operands are kept as `Number::Int` and evaluated with the existing
+    /// overflow-checked arithmetic; as soon as either operand of `+`, `-`, `*`, `/`
+    /// or `%` is a `Number::Float`, the result is promoted to `Number::Float`.
+    /// Division of two integers that divides evenly stays a `Number::Int`. An
+    /// `Assign` stores its value in `env` under the given name and also returns it,
+    /// so bindings made earlier in the session are visible to later expressions.
     ///
     /// # Errors
-    /// Returns an `anyhow::Error` in the following cases:
-    /// - Overflow on any operation
-    /// - Division by zero
-    pub fn eval(&self) -> Result<i64> {
+    /// Returns a `CalcError` in the following cases:
+    /// - Overflow on any integer operation
+    /// - Division or modulo by zero
+    /// - Integer-only operators (bitwise, shifts, power) applied to a `Float`
+    /// - Reference to an unknown variable or function
+    pub fn eval(&self, env: &mut Env) -> Result<Number, CalcError> {
         debug!("Eval: {:?}", self);
         match self {
-            Expr::Int(n) => Ok(*n),
-            Expr::Neg(e) => e
-                .eval()?
-                .checked_neg()
-                .ok_or(anyhow!("Overflow on negation")),
-            Expr::Add(a, b) => a
-                .eval()?
-                .checked_add(b.eval()?)
-                .ok_or(anyhow!("Overflow on addition")),
-            Expr::Sub(a, b) => a
-                .eval()?
-                .checked_sub(b.eval()?)
-                .ok_or(anyhow!("Overflow on substraction")),
-            Expr::Mul(a, b) => a
-                .eval()?
-                .checked_mul(b.eval()?)
-                .ok_or(anyhow!("Overflow on multiplication")),
-            Expr::Div(a, b) => {
-                let b = b.eval()?;
-                if b == 0 {
-                    bail!("Division by zero")
-                } else {
-                    a.eval()?
-                        .checked_div(b)
-                        .ok_or(anyhow!("Overflow on division"))
+            Expr::Num(n) => Ok(*n),
+            Expr::Var(name) => env
+                .get(name)
+                .copied()
+                .ok_or_else(|| CalcError::UnknownVariable { name: name.clone() }),
+            Expr::Assign(name, value) => {
+                let value = value.eval(env)?;
+                env.insert(name.clone(), value);
+                Ok(value)
+            }
+            Expr::Call(name, args) => Self::eval_call(name, args, env),
+            Expr::Neg(e) => match e.eval(env)? {
+                Number::Int(n) => n
+                    .checked_neg()
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "negation" }),
+                Number::Float(n) => Ok(Number::Float(-n)),
+            },
+            Expr::Add(a, b) => match (a.eval(env)?, b.eval(env)?) {
+                (Number::Int(x), Number::Int(y)) => x
+                    .checked_add(y)
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "addition" }),
+                (x, y) => Ok(Number::Float(x.as_f64() + y.as_f64())),
+            },
+            Expr::Sub(a, b) => match (a.eval(env)?, b.eval(env)?) {
+                (Number::Int(x), Number::Int(y)) => x
+                    .checked_sub(y)
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "substraction" }),
+                (x, y) => Ok(Number::Float(x.as_f64() - y.as_f64())),
+            },
+            Expr::Mul(a, b) => match (a.eval(env)?, b.eval(env)?) {
+                (Number::Int(x), Number::Int(y)) => x
+                    .checked_mul(y)
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "multiplication" }),
+                (x, y) => Ok(Number::Float(x.as_f64() * y.as_f64())),
+            },
+            Expr::Div(a, b) => match (a.eval(env)?, b.eval(env)?) {
+                (Number::Int(x), Number::Int(y)) => {
+                    if y == 0 {
+                        Err(CalcError::DivisionByZero)
+                    } else if x % y == 0 {
+                        x.checked_div(y)
+                            .map(Number::Int)
+                            .ok_or(CalcError::Overflow { op: "division" })
+                    } else {
+                        Ok(Number::Float(x as f64 / y as f64))
+                    }
+                }
+                (x, y) => {
+                    let y = y.as_f64();
+                    if y == 0.0 {
+                        Err(CalcError::DivisionByZero)
+                    } else {
+                        Ok(Number::Float(x.as_f64() / y))
+                    }
+                }
+            },
+            Expr::BitAnd(a, b) => Ok(Number::Int(
+                a.eval(env)?.as_i64("Bitwise AND")? & b.eval(env)?.as_i64("Bitwise AND")?,
+            )),
+            Expr::BitOr(a, b) => Ok(Number::Int(
+                a.eval(env)?.as_i64("Bitwise OR")? | b.eval(env)?.as_i64("Bitwise OR")?,
+            )),
+            Expr::BitXor(a, b) => Ok(Number::Int(
+                a.eval(env)?.as_i64("Bitwise XOR")? ^ b.eval(env)?.as_i64("Bitwise XOR")?,
+            )),
+            Expr::BitNot(e) => Ok(Number::Int(!e.eval(env)?.as_i64("Bitwise NOT")?)),
+            Expr::Shl(a, b) => {
+                let a = a.eval(env)?.as_i64("Left shift")?;
+                let b = b.eval(env)?.as_i64("Left shift")?;
+                let shift =
+                    u32::try_from(b).map_err(|_| CalcError::Overflow { op: "left shift" })?;
+                a.checked_shl(shift)
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "left shift" })
+            }
+            Expr::Shr(a, b) => {
+                let a = a.eval(env)?.as_i64("Right shift")?;
+                let b = b.eval(env)?.as_i64("Right shift")?;
+                let shift =
+                    u32::try_from(b).map_err(|_| CalcError::Overflow { op: "right shift" })?;
+                a.checked_shr(shift)
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "right shift" })
+            }
+            Expr::Mod(a, b) => match (a.eval(env)?, b.eval(env)?) {
+                (Number::Int(x), Number::Int(y)) => {
+                    if y == 0 {
+                        Err(CalcError::ModuloByZero)
+                    } else {
+                        x.checked_rem(y)
+                            .map(Number::Int)
+                            .ok_or(CalcError::Overflow { op: "modulo" })
+                    }
+                }
+                (x, y) => {
+                    let y = y.as_f64();
+                    if y == 0.0 {
+                        Err(CalcError::ModuloByZero)
+                    } else {
+                        Ok(Number::Float(x.as_f64() % y))
+                    }
+                }
+            },
+            Expr::Pow(a, b) => {
+                let base = a.eval(env)?.as_i64("Exponentiation")?;
+                let exponent = b.eval(env)?.as_i64("Exponentiation")?;
+                if exponent < 0 {
+                    return Err(CalcError::NegativeExponent);
                 }
+                let exponent = u32::try_from(exponent)
+                    .map_err(|_| CalcError::Overflow { op: "exponentiation" })?;
+                base.checked_pow(exponent)
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "exponentiation" })
             }
+            Expr::Abs(e) => match e.eval(env)? {
+                Number::Int(n) => n
+                    .checked_abs()
+                    .map(Number::Int)
+                    .ok_or(CalcError::Overflow { op: "abs" }),
+                Number::Float(n) => Ok(Number::Float(n.abs())),
+            },
+            Expr::Lt(a, b) => Self::eval_comparison(a, b, env, |x, y| x < y, |x, y| x < y),
+            Expr::Le(a, b) => Self::eval_comparison(a, b, env, |x, y| x <= y, |x, y| x <= y),
+            Expr::Gt(a, b) => Self::eval_comparison(a, b, env, |x, y| x > y, |x, y| x > y),
+            Expr::Ge(a, b) => Self::eval_comparison(a, b, env, |x, y| x >= y, |x, y| x >= y),
+            Expr::Eq(a, b) => Self::eval_comparison(a, b, env, |x, y| x == y, |x, y| x == y),
+            Expr::Ne(a, b) => Self::eval_comparison(a, b, env, |x, y| x != y, |x, y| x != y),
+        }
+    }
+
+    /// Evaluates both sides (preserving their own overflow/div-by-zero checks)
+    /// and reports the outcome as `Number::Int(1)` (true) or `Number::Int(0)`
+    /// (false). Like the arithmetic operators above, two `Number::Int`s are
+    /// compared exactly via `int_cmp`; widening to `f64` (and using
+    /// `float_cmp`) only happens when either side is a `Number::Float`, so
+    /// large `i64` values don't silently lose precision.
+    fn eval_comparison(
+        a: &Expr,
+        b: &Expr,
+        env: &mut Env,
+        int_cmp: impl Fn(i64, i64) -> bool,
+        float_cmp: impl Fn(f64, f64) -> bool,
+    ) -> Result<Number, CalcError> {
+        let result = match (a.eval(env)?, b.eval(env)?) {
+            (Number::Int(x), Number::Int(y)) => int_cmp(x, y),
+            (x, y) => float_cmp(x.as_f64(), y.as_f64()),
+        };
+        Ok(Number::Int(i64::from(result)))
+    }
+
+    /// Dispatches a call to one of the built-in functions (`sqrt`, `min`, `max`);
+    /// `abs(x)` and `pow(a, b)` are desugared to `Expr::Abs`/`Expr::Pow` at parse time
+    /// instead; `abs` or `pow` called with any other number of arguments reaches this
+    /// function and is reported as `CalcError::UnknownFunction`.
+    fn eval_call(name: &str, args: &[Expr], env: &mut Env) -> Result<Number, CalcError> {
+        let values = args
+            .iter()
+            .map(|arg| arg.eval(env))
+            .collect::<Result<Vec<_>, _>>()?;
+        match name {
+            "sqrt" => match values.as_slice() {
+                [n] => Ok(Number::Float(n.as_f64().sqrt())),
+                _ => Err(CalcError::WrongArgCount {
+                    name: "sqrt",
+                    expected: "1 argument",
+                    got: values.len(),
+                }),
+            },
+            "min" => match values.split_first() {
+                Some((first, rest)) => Ok(rest.iter().fold(*first, |acc, n| {
+                    if n.as_f64() < acc.as_f64() {
+                        *n
+                    } else {
+                        acc
+                    }
+                })),
+                None => Err(CalcError::WrongArgCount {
+                    name: "min",
+                    expected: "at least 1 argument",
+                    got: 0,
+                }),
+            },
+            "max" => match values.split_first() {
+                Some((first, rest)) => Ok(rest.iter().fold(*first, |acc, n| {
+                    if n.as_f64() > acc.as_f64() {
+                        *n
+                    } else {
+                        acc
+                    }
+                })),
+                None => Err(CalcError::WrongArgCount {
+                    name: "max",
+                    expected: "at least 1 argument",
+                    got: 0,
+                }),
+            },
+            other => Err(CalcError::UnknownFunction {
+                name: other.to_string(),
+            }),
         }
     }
 }
@@ -58,34 +266,114 @@ impl Expr {
 /// It holds:
 /// * A `TokenTranslator`, which converts raw characters into tokens
 /// * The current token
+/// * The char offset at which the current token started, for `CalcError` positions
 pub struct Parser<'a> {
     translator: TokenTranslator<'a>,
     current_token: Token,
+    current_pos: usize,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(input: &'a str) -> Result<Self> {
+    pub fn new(input: &'a str) -> Result<Self, CalcError> {
         let mut translator = TokenTranslator::new(input);
         let current_token = translator.next_token()?;
+        let current_pos = translator.last_token_pos();
         debug!("First token = {:?}", current_token);
         Ok(Parser {
             translator,
             current_token,
+            current_pos,
         })
     }
 
+    /// Parse a top-level statement and require that it consume the entire
+    /// input; anything left over (e.g. the trailing `< 3` in `1 < 2 < 3`,
+    /// since comparisons don't chain) is reported as an unexpected token
+    /// instead of being silently dropped.
+    pub fn parse_statement(&mut self) -> Result<Expr, CalcError> {
+        let expr = self.parse_assignment()?;
+        if matches!(self.current_token, Token::Eof) {
+            Ok(expr)
+        } else {
+            Err(CalcError::TrailingInput {
+                found: self.current_token.clone(),
+                pos: self.current_pos,
+            })
+        }
+    }
+
+    /// Parse a statement: either a plain expression or an assignment.
+    /// statement ::= IDENT "=" statement | comparison
+    /// Since the parser only looks one token ahead, an assignment is recognized
+    /// by first parsing a comparison and then checking for a following `=`;
+    /// this only makes sense if that comparison was a bare variable reference.
+    /// Assignment binds looser than comparison, so "x = 3+4 > 5" assigns `x`
+    /// the result of the comparison rather than comparing `x` itself.
+    /// For example: "x = 5" or "x = y = 2+3"
+    fn parse_assignment(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing statement from {:?}", self.current_token);
+        let pos = self.current_pos;
+        let expr = self.parse_comparison()?;
+        if matches!(self.current_token, Token::Assign) {
+            match expr {
+                Expr::Var(name) => {
+                    self.advance()?;
+                    let value = self.parse_assignment()?;
+                    let node = Expr::Assign(name, Box::new(value));
+                    debug!("New statement {:?}", node);
+                    Ok(node)
+                }
+                _ => Err(CalcError::InvalidAssignTarget { pos }),
+            }
+        } else {
+            Ok(expr)
+        }
+    }
+
+    /// Parse a comparison expression
+    /// comparison ::= expression (("<" | "<=" | ">" | ">=" | "==" | "!=") expression)?
+    /// Left-hand and right-hand sides are additive expressions; comparisons do
+    /// not chain (`1 < 2 < 3` is a parse error), following languages like
+    /// coreutils `expr` rather than Python's chained comparisons.
+    /// For example: "3+4 > 5"
+    fn parse_comparison(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing comparison from {:?}", self.current_token);
+        let node = self.parse_expr()?;
+        let operation = self.current_token.clone();
+        if matches!(
+            operation,
+            Token::Lt | Token::Le | Token::Gt | Token::Ge | Token::Eq | Token::Ne
+        ) {
+            self.advance()?;
+            let rhs = self.parse_expr()?;
+            let node = match operation {
+                Token::Lt => Expr::Lt(Box::new(node), Box::new(rhs)),
+                Token::Le => Expr::Le(Box::new(node), Box::new(rhs)),
+                Token::Gt => Expr::Gt(Box::new(node), Box::new(rhs)),
+                Token::Ge => Expr::Ge(Box::new(node), Box::new(rhs)),
+                Token::Eq => Expr::Eq(Box::new(node), Box::new(rhs)),
+                Token::Ne => Expr::Ne(Box::new(node), Box::new(rhs)),
+                _ => unreachable!("Unreachable"),
+            };
+            debug!("New comparison {:?}", node);
+            Ok(node)
+        } else {
+            Ok(node)
+        }
+    }
+
     /// Parse an additive expression
     /// An expression is a combination of numbers, and operations (addition, subtraction, multiplication, division)
     /// expression ::= term (("+" | "-") term)*
     /// For example: "2*3+5-6"
-    pub fn parse_expr(&mut self) -> Result<Expr> {
+    pub fn parse_expr(&mut self) -> Result<Expr, CalcError> {
         debug!("Start parsing expression from {:?}", self.current_token);
-        let mut node = self.parse_term()?;
+        let mut node = self.parse_bitor()?;
         while matches!(self.current_token, Token::Plus | Token::Minus) {
             let operation = self.current_token.clone();
             debug!("Operate: {:?}", operation);
             self.advance()?;
-            let rhs = self.parse_term()?;
+            let rhs = self.parse_bitor()?;
             node = match operation {
                 Token::Plus => Expr::Add(Box::new(node.clone()), Box::new(rhs)),
                 Token::Minus => Expr::Sub(Box::new(node.clone()), Box::new(rhs)),
@@ -96,21 +384,86 @@ impl<'a> Parser<'a> {
         Ok(node)
     }
 
+    /// Parse a bitwise-or expression
+    /// bitor ::= bitxor ("|" bitxor)*
+    fn parse_bitor(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing bitor from {:?}", self.current_token);
+        let mut node = self.parse_bitxor()?;
+        while matches!(self.current_token, Token::Pipe) {
+            self.advance()?;
+            let rhs = self.parse_bitxor()?;
+            node = Expr::BitOr(Box::new(node), Box::new(rhs));
+            debug!("New bitor {:?}", node);
+        }
+        Ok(node)
+    }
+
+    /// Parse a bitwise-xor expression
+    /// bitxor ::= bitand ("^" bitand)*
+    fn parse_bitxor(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing bitxor from {:?}", self.current_token);
+        let mut node = self.parse_bitand()?;
+        while matches!(self.current_token, Token::Caret) {
+            self.advance()?;
+            let rhs = self.parse_bitand()?;
+            node = Expr::BitXor(Box::new(node), Box::new(rhs));
+            debug!("New bitxor {:?}", node);
+        }
+        Ok(node)
+    }
+
+    /// Parse a bitwise-and expression
+    /// bitand ::= shift ("&" shift)*
+    fn parse_bitand(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing bitand from {:?}", self.current_token);
+        let mut node = self.parse_shift()?;
+        while matches!(self.current_token, Token::Ampersand) {
+            self.advance()?;
+            let rhs = self.parse_shift()?;
+            node = Expr::BitAnd(Box::new(node), Box::new(rhs));
+            debug!("New bitand {:?}", node);
+        }
+        Ok(node)
+    }
+
+    /// Parse a shift expression
+    /// shift ::= term (("<<" | ">>") term)*
+    fn parse_shift(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing shift from {:?}", self.current_token);
+        let mut node = self.parse_term()?;
+        while matches!(self.current_token, Token::Shl | Token::Shr) {
+            let operation = self.current_token.clone();
+            self.advance()?;
+            let rhs = self.parse_term()?;
+            node = match operation {
+                Token::Shl => Expr::Shl(Box::new(node), Box::new(rhs)),
+                Token::Shr => Expr::Shr(Box::new(node), Box::new(rhs)),
+                _ => unreachable!("Unreachable"),
+            };
+            debug!("New shift {:?}", node);
+        }
+        Ok(node)
+    }
+
     /// Parse a term
     /// A term is multiplicative expression:
     /// term ::= factor (("*" | "/") factor)*
     /// For example: "2 * 3 / 4"
-    fn parse_term(&mut self) -> Result<Expr> {
+    fn parse_term(&mut self) -> Result<Expr, CalcError> {
         debug!("Start parsing term from {:?}", self.current_token);
-        let mut node = self.parse_factor()?;
-        while matches!(self.current_token, Token::Asterisk | Token::Slash) {
+        let mut node = self.parse_power()?;
+        while matches!(
+            self.current_token,
+            Token::Asterisk | Token::Slash | Token::Percent
+        ) {
             let operation = self.current_token.clone();
             debug!("Operate: {:?}", operation);
             self.advance()?;
-            let rhs = self.parse_factor()?;
+            let rhs = self.parse_power()?;
             node = match operation {
                 Token::Asterisk => Expr::Mul(Box::new(node.clone()), Box::new(rhs)),
                 Token::Slash => Expr::Div(Box::new(node.clone()), Box::new(rhs)),
+                Token::Percent => Expr::Mod(Box::new(node.clone()), Box::new(rhs)),
                 _ => unreachable!("Unreachable"),
             };
             debug!("New term {:?}", node);
@@ -118,6 +471,23 @@ impl<'a> Parser<'a> {
         Ok(node)
     }
 
+    /// Parse a power expression
+    /// power ::= factor ("**" power)?
+    /// Right-associative, so `2**3**2` parses as `2**(3**2)`.
+    fn parse_power(&mut self) -> Result<Expr, CalcError> {
+        debug!("Start parsing power from {:?}", self.current_token);
+        let base = self.parse_factor()?;
+        if matches!(self.current_token, Token::Pow) {
+            self.advance()?;
+            let rhs = self.parse_power()?;
+            let node = Expr::Pow(Box::new(base), Box::new(rhs));
+            debug!("New power {:?}", node);
+            Ok(node)
+        } else {
+            Ok(base)
+        }
+    }
+
     /// Parse a primary expression:
     /// factor ::= INT | "-" factor | "(" expression ")"
     ///
@@ -125,9 +495,9 @@ impl<'a> Parser<'a> {
     /// - Integer literals (i.e. 42)
     /// - Negative numbers (i.e. -7)
     /// - Parenthesized sub-expressions (i.e. (1+2))
-    fn parse_factor(&mut self) -> Result<Expr> {
+    fn parse_factor(&mut self) -> Result<Expr, CalcError> {
         debug!("Parse {:?} as factor", self.current_token);
-        match self.current_token {
+        match self.current_token.clone() {
             Token::Minus => {
                 self.advance()?;
                 let factor = self.parse_factor()?;
@@ -135,26 +505,88 @@ impl<'a> Parser<'a> {
                 debug!("New factor {:?}", factor);
                 Ok(factor)
             }
-            Token::Int(n) => {
+            Token::Tilde => {
+                self.advance()?;
+                let factor = self.parse_factor()?;
+                let factor = Expr::BitNot(Box::new(factor));
+                debug!("New factor {:?}", factor);
+                Ok(factor)
+            }
+            Token::Num(n) => {
                 self.advance()?;
-                let factor = Expr::Int(n);
+                let factor = Expr::Num(n);
                 debug!("New factor {:?}", factor);
                 Ok(factor)
             }
             Token::LeftParenthesis => {
+                let open_pos = self.current_pos;
                 self.advance()?;
                 let expr = self.parse_expr()?;
+                if !matches!(self.current_token, Token::RightParenthesis) {
+                    return Err(CalcError::UnmatchedParen { pos: open_pos });
+                }
                 self.advance()?;
                 debug!("New expression (via factor) {:?}", expr);
                 Ok(expr)
             }
-            _ => bail!("Unexpected token in factor: {:?}", self.current_token),
+            Token::Ident(name) => {
+                self.advance()?;
+                if matches!(self.current_token, Token::LeftParenthesis) {
+                    self.parse_call(name)
+                } else {
+                    let factor = Expr::Var(name);
+                    debug!("New factor {:?}", factor);
+                    Ok(factor)
+                }
+            }
+            _ => Err(CalcError::UnexpectedToken {
+                found: self.current_token.clone(),
+                pos: self.current_pos,
+            }),
         }
     }
 
-    fn advance(&mut self) -> Result<()> {
+    /// Parse a function call, `self.current_token` being the opening `(`:
+    /// `name "(" (expr ("," expr)*)? ")"`.
+    /// Desugars `abs(x)`/`pow(a, b)` to the dedicated `Expr::Abs`/`Expr::Pow` nodes;
+    /// any other call (including `abs`/`pow` with another argument count) becomes a
+    /// generic `Expr::Call` resolved at evaluation time.
+    fn parse_call(&mut self, name: String) -> Result<Expr, CalcError> {
+        let open_pos = self.current_pos;
+        self.advance()?;
+        let mut args = Vec::new();
+        if !matches!(self.current_token, Token::RightParenthesis) {
+            loop {
+                args.push(self.parse_expr()?);
+                if matches!(self.current_token, Token::Comma) {
+                    self.advance()?;
+                } else {
+                    break;
+                }
+            }
+        }
+        if !matches!(self.current_token, Token::RightParenthesis) {
+            return Err(CalcError::UnmatchedParen { pos: open_pos });
+        }
+        self.advance()?;
+        let factor = match (name.as_str(), args.len()) {
+            ("abs", 1) => Expr::Abs(Box::new(args.into_iter().next().unwrap())),
+            ("pow", 2) => {
+                let mut args = args.into_iter();
+                let base = args.next().unwrap();
+                let exponent = args.next().unwrap();
+                Expr::Pow(Box::new(base), Box::new(exponent))
+            }
+            _ => Expr::Call(name, args),
+        };
+        debug!("New factor {:?}", factor);
+        Ok(factor)
+    }
+
+    fn advance(&mut self) -> Result<(), CalcError> {
         trace!("Advance");
         self.current_token = self.translator.next_token()?;
+        self.current_pos = self.translator.last_token_pos();
         Ok(())
     }
 }
@@ -1,21 +1,78 @@
-use anyhow::{bail, Result};
+use crate::error::CalcError;
+use std::fmt;
+
+/// A numeric value produced by the lexer or an evaluation, either an integer
+/// or a floating-point number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// Returns the value as an `f64`, widening an `Int` if necessary.
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    /// Returns the value as an `i64`, or an error naming `op` if it is a `Float`.
+    pub fn as_i64(self, op: &'static str) -> Result<i64, CalcError> {
+        match self {
+            Number::Int(n) => Ok(n),
+            Number::Float(_) => Err(CalcError::NonIntegerOperand { op }),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{n}"),
+            Number::Float(n) => write!(f, "{n}"),
+        }
+    }
+}
 
 /// Tokens representing the units of a methematical expression.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    Int(i64),
+    Num(Number),
     Plus,
     Minus,
     Asterisk,
     Slash,
     LeftParenthesis,
     RightParenthesis,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Shl,
+    Shr,
+    Percent,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Ident(String),
+    Assign,
+    Comma,
     Eof,
 }
 
-/// Converts a string input into a sequence of `Token`s
+/// Converts a string input into a sequence of `Token`s, tracking the char
+/// offset of each token so that `Parser` can attach source positions to
+/// `CalcError`s.
 pub struct TokenTranslator<'a> {
     chars: std::iter::Peekable<std::str::Chars<'a>>,
+    pos: usize,
+    last_token_pos: usize,
 }
 
 impl<'a> TokenTranslator<'a> {
@@ -23,35 +80,184 @@ impl<'a> TokenTranslator<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             chars: input.chars().peekable(),
+            pos: 0,
+            last_token_pos: 0,
+        }
+    }
+
+    /// The char offset at which the most recently returned token started.
+    pub fn last_token_pos(&self) -> usize {
+        self.last_token_pos
+    }
+
+    /// Consumes and returns the next char, advancing `pos`.
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c.is_some() {
+            self.pos += 1;
         }
+        c
     }
 
     /// Returns the next token from the input string.
     ///
     /// # Errors
-    /// Returns an `anyhow::Error` in the following cases:
-    /// - Int64 parsing failure
+    /// Returns a `CalcError` in the following cases:
+    /// - Int64/float literal parsing failure
     /// - Unexpected character/symbol in the input string
-    pub fn next_token(&mut self) -> Result<Token> {
-        match self.chars.next() {
-            Some(' ') => self.next_token(),
+    pub fn next_token(&mut self) -> Result<Token, CalcError> {
+        while matches!(self.chars.peek(), Some(' ')) {
+            self.bump();
+        }
+        self.last_token_pos = self.pos;
+        let start = self.pos;
+        match self.bump() {
             Some('+') => Ok(Token::Plus),
             Some('-') => Ok(Token::Minus),
+            Some('*') if self.chars.peek() == Some(&'*') => {
+                self.bump();
+                Ok(Token::Pow)
+            }
             Some('*') => Ok(Token::Asterisk),
             Some('/') => Ok(Token::Slash),
+            Some('%') => Ok(Token::Percent),
             Some('(') => Ok(Token::LeftParenthesis),
             Some(')') => Ok(Token::RightParenthesis),
+            Some(',') => Ok(Token::Comma),
+            Some('=') if self.chars.peek() == Some(&'=') => {
+                self.bump();
+                Ok(Token::Eq)
+            }
+            Some('=') => Ok(Token::Assign),
+            Some('!') if self.chars.peek() == Some(&'=') => {
+                self.bump();
+                Ok(Token::Ne)
+            }
+            Some('&') => Ok(Token::Ampersand),
+            Some('|') => Ok(Token::Pipe),
+            Some('^') => Ok(Token::Caret),
+            Some('~') => Ok(Token::Tilde),
+            Some('<') if self.chars.peek() == Some(&'<') => {
+                self.bump();
+                Ok(Token::Shl)
+            }
+            Some('<') if self.chars.peek() == Some(&'=') => {
+                self.bump();
+                Ok(Token::Le)
+            }
+            Some('<') => Ok(Token::Lt),
+            Some('>') if self.chars.peek() == Some(&'>') => {
+                self.bump();
+                Ok(Token::Shr)
+            }
+            Some('>') if self.chars.peek() == Some(&'=') => {
+                self.bump();
+                Ok(Token::Ge)
+            }
+            Some('>') => Ok(Token::Gt),
+            Some('0') if matches!(self.chars.peek(), Some('x' | 'X')) => {
+                self.bump();
+                self.read_radix_int(start, 16, |c| c.is_ascii_hexdigit())
+            }
+            Some('0') if matches!(self.chars.peek(), Some('b' | 'B')) => {
+                self.bump();
+                self.read_radix_int(start, 2, |c| matches!(c, '0' | '1'))
+            }
+            Some('0') if matches!(self.chars.peek(), Some('o' | 'O')) => {
+                self.bump();
+                self.read_radix_int(start, 8, |c| matches!(c, '0'..='7'))
+            }
             Some(ch) if ch.is_ascii_digit() => {
-                let mut digits = ch.to_string();
+                let mut literal = ch.to_string();
+                let mut is_float = false;
                 while let Some(&d @ '0'..='9') = self.chars.peek() {
-                    digits.push(d);
-                    self.chars.next();
+                    literal.push(d);
+                    self.bump();
                 }
-                let num = digits.parse()?;
-                Ok(Token::Int(num))
+                if self.chars.peek() == Some(&'.') {
+                    is_float = true;
+                    literal.push('.');
+                    self.bump();
+                    while let Some(&d @ '0'..='9') = self.chars.peek() {
+                        literal.push(d);
+                        self.bump();
+                    }
+                }
+                if matches!(self.chars.peek(), Some('e' | 'E')) {
+                    is_float = true;
+                    literal.push('e');
+                    self.bump();
+                    if matches!(self.chars.peek(), Some('+' | '-')) {
+                        literal.push(self.bump().expect("peeked"));
+                    }
+                    while let Some(&d @ '0'..='9') = self.chars.peek() {
+                        literal.push(d);
+                        self.bump();
+                    }
+                }
+                if is_float {
+                    let num: f64 = literal
+                        .parse()
+                        .map_err(|e: std::num::ParseFloatError| CalcError::InvalidNumber {
+                            pos: start,
+                            message: e.to_string(),
+                        })?;
+                    Ok(Token::Num(Number::Float(num)))
+                } else {
+                    let num: i64 = literal
+                        .parse()
+                        .map_err(|e: std::num::ParseIntError| CalcError::InvalidNumber {
+                            pos: start,
+                            message: e.to_string(),
+                        })?;
+                    Ok(Token::Num(Number::Int(num)))
+                }
+            }
+            Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {
+                let mut word = ch.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        word.push(c);
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                Ok(Token::Ident(word))
             }
             None => Ok(Token::Eof),
-            ch => bail!("Unexpected character: '{:?}'", ch),
+            Some(ch) => Err(CalcError::UnexpectedChar { ch, pos: start }),
+        }
+    }
+
+    /// Reads the digits of a radix-prefixed integer literal (`0x`, `0b`, `0o`) and
+    /// parses them with `i64::from_str_radix`. `start` is the offset of the `0`
+    /// that began the literal, used to report parse errors.
+    fn read_radix_int(
+        &mut self,
+        start: usize,
+        radix: u32,
+        is_digit: impl Fn(char) -> bool,
+    ) -> Result<Token, CalcError> {
+        let mut digits = String::new();
+        while let Some(&d) = self.chars.peek() {
+            if is_digit(d) {
+                digits.push(d);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if digits.is_empty() {
+            return Err(CalcError::InvalidNumber {
+                pos: start,
+                message: "expected digits after radix prefix".to_string(),
+            });
         }
+        let num = i64::from_str_radix(&digits, radix).map_err(|e| CalcError::InvalidNumber {
+            pos: start,
+            message: e.to_string(),
+        })?;
+        Ok(Token::Num(Number::Int(num)))
     }
 }